@@ -1,8 +1,9 @@
 use super::KeywordToken;
 use proc_macro2::TokenStream;
 use quote::ToTokens;
+use std::collections::HashMap;
 use syn::{
-    braced, parenthesized,
+    braced, bracketed, parenthesized,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
     spanned::Spanned,
@@ -12,6 +13,30 @@ use syn::{
 
 type Fields<T> = Punctuated<T, Token![,]>;
 
+/// `Token::display()` returns the keyword already wrapped in backticks
+/// (e.g. `` `test` ``); this strips them so callers can embed the bare
+/// keyword text in their own quoted spans.
+fn bare_keyword<T: Token>() -> &'static str {
+    T::display().trim_matches('`')
+}
+
+/// Reports the human-readable keyword text of a parsed [`KeywordToken`]:
+/// the display text of its `KeywordToken::Token` type, without backticks.
+/// Every `KeywordToken` type gets this through the blanket impl below, so
+/// the text is the same for every instance of a type; an item type that
+/// can hold several different keywords (such as an enum of sub-attributes)
+/// reports the keyword of its `Token` type, not the one that was parsed.
+pub(crate) trait KeywordDisplay: KeywordToken
+where
+    Self::Token: Token,
+{
+    fn keyword_display(&self) -> &'static str {
+        bare_keyword::<Self::Token>()
+    }
+}
+
+impl<T: KeywordToken> KeywordDisplay for T where T::Token: Token {}
+
 /// `MetaExpr` represents a key/expr pair
 /// Takes two forms:
 /// * ident(expr)
@@ -47,16 +72,40 @@ impl<Keyword: Token + Spanned> KeywordToken for MetaVoid<Keyword> {
     }
 }
 
-impl<Keyword: Parse, Value: Parse> Parse for MetaValue<Keyword, Value> {
+impl<Keyword: Parse + Token + Spanned, Value: Parse> Parse for MetaValue<Keyword, Value> {
     fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
-        let ident = input.parse()?;
+        let ident: Keyword = input.parse()?;
         let value = if input.peek(token::Paren) {
             let content;
             parenthesized!(content in input);
-            content.parse()?
-        } else {
+            let value = content.parse()?;
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+                // A lone trailing comma (`test(3u8,)`) is just a single
+                // value; only a second item after it is a confused repeat.
+                if !content.is_empty() {
+                    let extra_span = content.cursor().span();
+                    return Err(syn::Error::new(
+                        extra_span,
+                        format!(
+                            "{} takes a single value — did you mean to repeat the attribute?",
+                            Keyword::display()
+                        ),
+                    ));
+                }
+            }
+            value
+        } else if input.peek(Token![=]) {
             input.parse::<Token![=]>()?;
             input.parse()?
+        } else {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "expected `{0} = ...` or `{0}(...)`",
+                    bare_keyword::<Keyword>()
+                ),
+            ));
         };
 
         Ok(MetaValue { ident, value })
@@ -83,6 +132,54 @@ impl<Keyword: Token + Spanned, Value> KeywordToken for MetaValue<Keyword, Value>
     }
 }
 
+/// `MetaBlockExpr` represents a key/block pair, used as a multi-statement
+/// alternative to the single-expression forms of `MetaExpr`.
+/// Takes the form:
+/// * ident { stmt; stmt; tail_expr }
+#[derive(Debug, Clone)]
+pub(crate) struct MetaBlockExpr<Keyword> {
+    pub(crate) ident: Keyword,
+    pub(crate) block: syn::Block,
+}
+
+impl<Keyword: Parse> Parse for MetaBlockExpr<Keyword> {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        let content;
+        let brace_token = braced!(content in input);
+        let stmts = content.call(syn::Block::parse_within)?;
+        Ok(MetaBlockExpr {
+            ident,
+            block: syn::Block { brace_token, stmts },
+        })
+    }
+}
+
+impl<Keyword> From<MetaBlockExpr<Keyword>> for TokenStream {
+    fn from(value: MetaBlockExpr<Keyword>) -> Self {
+        value.into_token_stream()
+    }
+}
+
+impl<Keyword> ToTokens for MetaBlockExpr<Keyword> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        Expr::Block(syn::ExprBlock {
+            attrs: Vec::new(),
+            label: None,
+            block: self.block.clone(),
+        })
+        .to_tokens(tokens);
+    }
+}
+
+impl<Keyword: Token + Spanned> KeywordToken for MetaBlockExpr<Keyword> {
+    type Token = Keyword;
+
+    fn keyword_span(&self) -> proc_macro2::Span {
+        self.ident.span()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct MetaVoid<Keyword> {
     pub(crate) ident: Keyword,
@@ -127,29 +224,35 @@ impl<Keyword: Token + Spanned, ItemType> KeywordToken for MetaList<Keyword, Item
 }
 
 #[derive(Debug, Clone)]
-pub(crate) enum Enclosure<ParenType, BraceType> {
+pub(crate) enum Enclosure<ParenType, BraceType, BracketType = BraceType> {
     Paren { fields: Fields<ParenType> },
     Brace { fields: Fields<BraceType> },
+    Bracket { fields: Fields<BracketType> },
 }
 
 #[derive(Debug, Clone)]
-pub(crate) struct MetaEnclosedList<Keyword, ParenItemType, BraceItemType> {
+pub(crate) struct MetaEnclosedList<
+    Keyword,
+    ParenItemType,
+    BraceItemType,
+    BracketItemType = BraceItemType,
+> {
     pub(crate) ident: Keyword,
-    pub(crate) list: Enclosure<ParenItemType, BraceItemType>,
+    pub(crate) list: Enclosure<ParenItemType, BraceItemType, BracketItemType>,
 }
 
-impl<Keyword, ParenItemType, BraceItemType> Parse
-    for MetaEnclosedList<Keyword, ParenItemType, BraceItemType>
+impl<Keyword, ParenItemType, BraceItemType, BracketItemType> Parse
+    for MetaEnclosedList<Keyword, ParenItemType, BraceItemType, BracketItemType>
 where
-    Keyword: Parse,
+    Keyword: Parse + Token + Spanned,
     ParenItemType: Parse,
     BraceItemType: Parse,
+    BracketItemType: Parse,
 {
     fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
-        let ident = input.parse()?;
+        let ident: Keyword = input.parse()?;
         let content;
-        let lookahead = input.lookahead1();
-        if lookahead.peek(token::Paren) {
+        if input.peek(token::Paren) {
             parenthesized!(content in input);
             Ok(Self {
                 ident,
@@ -157,7 +260,7 @@ where
                     fields: content.parse_terminated::<_, Token![,]>(ParenItemType::parse)?,
                 },
             })
-        } else if lookahead.peek(token::Brace) {
+        } else if input.peek(token::Brace) {
             braced!(content in input);
             Ok(Self {
                 ident,
@@ -165,14 +268,28 @@ where
                     fields: content.parse_terminated::<_, Token![,]>(BraceItemType::parse)?,
                 },
             })
+        } else if input.peek(token::Bracket) {
+            bracketed!(content in input);
+            Ok(Self {
+                ident,
+                list: Enclosure::Bracket {
+                    fields: content.parse_terminated::<_, Token![,]>(BracketItemType::parse)?,
+                },
+            })
         } else {
-            Err(lookahead.error())
+            Err(syn::Error::new(
+                input.span(),
+                format!(
+                    "{} expects `(...)`, `{{ ... }}`, or `[...]`",
+                    bare_keyword::<Keyword>()
+                ),
+            ))
         }
     }
 }
 
-impl<Keyword: Token + Spanned, ParenItemType, BraceItemType> KeywordToken
-    for MetaEnclosedList<Keyword, ParenItemType, BraceItemType>
+impl<Keyword: Token + Spanned, ParenItemType, BraceItemType, BracketItemType> KeywordToken
+    for MetaEnclosedList<Keyword, ParenItemType, BraceItemType, BracketItemType>
 {
     type Token = Keyword;
 
@@ -181,6 +298,26 @@ impl<Keyword: Token + Spanned, ParenItemType, BraceItemType> KeywordToken
     }
 }
 
+/// `MetaTypeCount` represents a type paired with a bracketed count
+/// expression, used to describe "N elements of T" in a single token group.
+/// Takes the form:
+/// * ty[expr]
+#[derive(Debug, Clone)]
+pub(crate) struct MetaTypeCount {
+    pub(crate) ty: Type,
+    pub(crate) count: Box<Expr>,
+}
+
+impl Parse for MetaTypeCount {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let ty = input.parse()?;
+        let content;
+        bracketed!(content in input);
+        let count = content.parse()?;
+        Ok(MetaTypeCount { ty, count })
+    }
+}
+
 // This is like `syn::PatType` except:
 // (1) Implements `Parse`;
 // (2) No attributes;
@@ -228,6 +365,7 @@ impl Parse for IdentTypeMaybeDefault {
     }
 }
 
+#[derive(Debug, Clone)]
 pub(crate) struct MetaAttrList<P>(Fields<P>);
 
 impl<P> MetaAttrList<P> {
@@ -236,6 +374,34 @@ impl<P> MetaAttrList<P> {
     }
 }
 
+impl<P: KeywordDisplay> MetaAttrList<P>
+where
+    P::Token: Token,
+{
+    /// Checks that none of the keywords named in `unique` appears more than
+    /// once in this list, returning a combined error (primary label on the
+    /// repeat, secondary on the first occurrence) if a duplicate is found.
+    /// Keywords not named in `unique` are allowed to repeat freely.
+    pub(crate) fn no_duplicates(self, unique: &[&str]) -> syn::Result<Self> {
+        let mut seen = HashMap::new();
+        for item in &self.0 {
+            let keyword = item.keyword_display();
+            if !unique.contains(&keyword) {
+                continue;
+            }
+            let span = item.keyword_span();
+            if let Some(&first_span) = seen.get(keyword) {
+                let mut error =
+                    syn::Error::new(span, format!("{keyword} specified more than once"));
+                error.combine(syn::Error::new(first_span, "first occurrence here"));
+                return Err(error);
+            }
+            seen.insert(keyword, span);
+        }
+        Ok(self)
+    }
+}
+
 impl<P: Parse> Parse for MetaAttrList<P> {
     fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
         let content;
@@ -255,6 +421,7 @@ mod tests {
     }
 
     type MetaValueTest = MetaValue<kw::test, Lit>;
+    type MetaBlockExprTest = MetaBlockExpr<kw::test>;
     type MetaListTest = MetaList<kw::test_list, Lit>;
     type MetaAttrListTest = MetaAttrList<Lit>;
     type MetaEnclosedListTest = MetaEnclosedList<kw::test_enclosed_list, Lit, Lit>;
@@ -284,6 +451,7 @@ mod tests {
 
     try_parse!(meta_value_assign, MetaValueTest, { test = 3u8 });
     try_parse!(meta_value_paren, MetaValueTest, { test(b"TEST") });
+    try_parse!(meta_value_paren_trailing_comma, MetaValueTest, { test(3u8,) });
     try_parse_fail!(meta_value_missing_keyword, MetaValueTest, { = 3u8 });
     try_parse_fail!(meta_value_missing_value, MetaValueTest, { test });
     try_parse_fail!(meta_value_wrong_keyword, MetaValueTest, { wrong = 3u8 });
@@ -292,6 +460,21 @@ mod tests {
         test(3u8, 3u8)
     });
 
+    #[test]
+    #[cfg_attr(coverage_nightly, no_coverage)]
+    fn meta_value_missing_value_message() {
+        let error = syn::parse2::<MetaValueTest>(quote::quote! { test }).unwrap_err();
+        assert!(error.to_string().contains("expected"));
+        assert!(error.to_string().contains("test"));
+    }
+
+    #[test]
+    #[cfg_attr(coverage_nightly, no_coverage)]
+    fn meta_value_confused_as_list_message() {
+        let error = syn::parse2::<MetaValueTest>(quote::quote! { test(3u8, 3u8) }).unwrap_err();
+        assert!(error.to_string().contains("takes a single value"));
+    }
+
     #[test]
     #[cfg_attr(coverage_nightly, no_coverage)]
     fn meta_value_into_tokenstream() {
@@ -322,6 +505,41 @@ mod tests {
         );
     }
 
+    try_parse!(meta_block_expr, MetaBlockExprTest, {
+        test {
+            let x = 1u8;
+            x
+        }
+    });
+    try_parse!(meta_block_expr_empty, MetaBlockExprTest, { test {} });
+    try_parse_fail!(meta_block_expr_missing_keyword, MetaBlockExprTest, {
+        {
+            1u8
+        }
+    });
+    try_parse_fail!(meta_block_expr_not_braced, MetaBlockExprTest, { test(1u8) });
+
+    #[test]
+    #[cfg_attr(coverage_nightly, no_coverage)]
+    fn meta_block_expr_to_tokens() {
+        let expected = quote::quote! {
+            {
+                let x = 1u8;
+                x
+            }
+        };
+        let value = syn::parse2::<MetaBlockExprTest>(quote::quote! {
+            test {
+                let x = 1u8;
+                x
+            }
+        })
+        .unwrap();
+        let mut actual = TokenStream::new();
+        value.to_tokens(&mut actual);
+        assert_eq!(expected.to_string(), actual.to_string());
+    }
+
     try_parse!(meta_list, MetaListTest, { test_list(3u8, 3u8) });
     try_parse!(meta_list_empty, MetaListTest, { test_list() });
     try_parse_fail!(meta_list_missing_keyword, MetaListTest, { (3u8, 3u8) });
@@ -348,11 +566,27 @@ mod tests {
     try_parse_fail!(meta_enclosed_list_wrong_delimiter, MetaEnclosedListTest, {
         test_enclosed_list = (3u8, 3u8)
     });
-    try_parse_fail!(meta_enclosed_list_wrong_bracket_kind, MetaEnclosedListTest, { test_enclosed_list [] });
+    try_parse!(meta_enclosed_list_bracket, MetaEnclosedListTest, { test_enclosed_list [3u8, 3u8] });
+    try_parse!(meta_enclosed_list_bracket_empty, MetaEnclosedListTest, {
+        test_enclosed_list []
+    });
     try_parse_fail!(meta_enclosed_list_wrong_item_type, MetaEnclosedListTest, {
         test_enclosed_list(i32)
     });
 
+    #[test]
+    #[cfg_attr(coverage_nightly, no_coverage)]
+    fn meta_enclosed_list_wrong_delimiter_message() {
+        let error =
+            syn::parse2::<MetaEnclosedListTest>(quote::quote! { test_enclosed_list }).unwrap_err();
+        assert!(error.to_string().contains("expects"));
+    }
+
+    try_parse!(meta_type_count, MetaTypeCount, { u8[3 + 1] });
+    try_parse!(meta_type_count_zero, MetaTypeCount, { u8[0] });
+    try_parse_fail!(meta_type_count_missing_brackets, MetaTypeCount, { u8 });
+    try_parse_fail!(meta_type_count_missing_type, MetaTypeCount, { [3] });
+
     #[test]
     #[cfg_attr(coverage_nightly, no_coverage)]
     fn meta_list_keyword_token() {
@@ -395,4 +629,33 @@ mod tests {
         let value = syn::parse2::<MetaAttrListTest>(quote::quote! { (1u8, 2u8, 3u8) }).unwrap();
         assert_eq!(expected, value.into_iter().collect::<Vec<_>>()[..]);
     }
+
+    #[test]
+    #[cfg_attr(coverage_nightly, no_coverage)]
+    fn meta_attr_list_no_duplicates_ok() {
+        let value =
+            syn::parse2::<MetaAttrList<MetaValueTest>>(quote::quote! { (test = 1u8) }).unwrap();
+        assert!(value.no_duplicates(&["test"]).is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(coverage_nightly, no_coverage)]
+    fn meta_attr_list_no_duplicates_err() {
+        let value = syn::parse2::<MetaAttrList<MetaValueTest>>(quote::quote! {
+            (test = 1u8, test = 2u8)
+        })
+        .unwrap();
+        let error = value.no_duplicates(&["test"]).unwrap_err();
+        assert!(error.to_string().contains("specified more than once"));
+    }
+
+    #[test]
+    #[cfg_attr(coverage_nightly, no_coverage)]
+    fn meta_attr_list_no_duplicates_ignores_non_unique_keywords() {
+        let value = syn::parse2::<MetaAttrList<MetaValueTest>>(quote::quote! {
+            (test = 1u8, test = 2u8)
+        })
+        .unwrap();
+        assert!(value.no_duplicates(&[]).is_ok());
+    }
 }